@@ -1,7 +1,10 @@
-use std::time::Duration;
+use std::convert::TryInto;
+use std::process::Command as ShellCommand;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 
-use futures_timer::ext::TryFutureExt;
-use serde::Deserialize;
+use futures_timer::Delay;
+use serde::{Deserialize, Serialize};
 use clap::{Arg, App, SubCommand, ArgMatches};
 use snafu::Snafu;
 use prettytable::{Table, row, cell};
@@ -18,6 +21,14 @@ enum Error {
     RemoteAPIError{},
     #[snafu(display("Error reading remote API"))]
     CommandNotFound,
+    #[snafu(display("Could not authenticate against the remote API"))]
+    AuthenticationFailed,
+    #[snafu(display("Could not access the OS keyring"))]
+    KeyringError,
+    #[snafu(display("A pre-command hook failed"))]
+    HookFailed,
+    #[snafu(display("A post-command hook failed"))]
+    PostHookFailed,
 }
 
 #[derive(Eq, PartialEq, Debug)]
@@ -29,19 +40,199 @@ enum Command {
     CreatePerson(PersonParams),
     AddPersonToQuestionnaire{id: String, email: String},
     RemovePersonFromQuestionnaire{id: String, email: String},
+    Login{app: String},
     Unexpected,
 }
 
+impl Command {
+    fn name(&self) -> &'static str {
+        use Command::*;
+
+        match self {
+            Healthcheck => "health",
+            ShowPeople | ShowQuestionnaires => "show",
+            DeletePerson{..} => "delete",
+            CreatePerson(_) => "create",
+            AddPersonToQuestionnaire{..} | RemovePersonFromQuestionnaire{..} => "update",
+            Login{..} => "login",
+            Unexpected => "unexpected",
+        }
+    }
+
+    fn email(&self) -> Option<&str> {
+        use Command::*;
+
+        match self {
+            DeletePerson{email} => Some(email),
+            CreatePerson(params) => params.get("email").map(|email| email.as_str()),
+            AddPersonToQuestionnaire{email, ..} => Some(email),
+            RemovePersonFromQuestionnaire{email, ..} => Some(email),
+            _ => None,
+        }
+    }
+
+    fn questionnaire_id(&self) -> Option<&str> {
+        use Command::*;
+
+        match self {
+            AddPersonToQuestionnaire{id, ..} => Some(id),
+            RemovePersonFromQuestionnaire{id, ..} => Some(id),
+            _ => None,
+        }
+    }
+}
+
+enum HttpMethod {
+    Get,
+    Post,
+    Put,
+    Delete,
+}
+
+#[derive(Clone, Copy)]
+enum OutputFormat {
+    Table,
+    Json,
+    Csv,
+}
+
 enum Authentication {
     None,
-    Token(String)
+    Token(Secret),
+    ClientCredentials {
+        client_id: Secret,
+        client_secret: Secret,
+        token_url: String,
+        scope: Option<String>,
+    },
 }
 
 #[derive(Deserialize, Debug)]
+struct OAuthConfig {
+    client_id: Secret,
+    client_secret: Secret,
+    token_url: String,
+    scope: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    expires_in: u64,
+}
+
+#[derive(Debug)]
+struct CachedToken {
+    access_token: Secret,
+    expires_at: Instant,
+}
+
+#[derive(Clone, Deserialize)]
+#[serde(transparent)]
+struct Secret(String);
+
+impl Secret {
+    fn expose_secret(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Debug for Secret {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "Secret(<redacted>)")
+    }
+}
+
+impl std::fmt::Display for Secret {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "<redacted>")
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+struct HttpConfig {
+    timeout_secs: Option<u64>,
+    retries: Option<u32>,
+    retry_backoff_ms: Option<u64>,
+    proxy: Option<String>,
+    resolve: Option<Vec<String>>,
+}
+
+impl HttpConfig {
+    fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout_secs.unwrap_or(5))
+    }
+
+    fn retries(&self) -> u32 {
+        self.retries.unwrap_or(0)
+    }
+
+    fn retry_backoff(&self) -> Duration {
+        Duration::from_millis(self.retry_backoff_ms.unwrap_or(250))
+    }
+
+    fn apply_dns_overrides(&self, endpoint: &str) -> (String, Option<String>) {
+        let overrides = match &self.resolve {
+            Some(entries) => entries,
+            None => return (endpoint.to_owned(), None),
+        };
+
+        let mut url = match surf::Url::parse(endpoint) {
+            Ok(url) => url,
+            Err(_) => return (endpoint.to_owned(), None),
+        };
+
+        let host = match url.host_str() {
+            Some(host) => host.to_owned(),
+            None => return (endpoint.to_owned(), None),
+        };
+
+        for entry in overrides {
+            let (override_host, ip) = match entry.split_once(':') {
+                Some(pair) => pair,
+                None => continue,
+            };
+
+            if override_host != host {
+                continue;
+            }
+
+            if url.set_host(Some(ip)).is_err() {
+                return (endpoint.to_owned(), None);
+            }
+
+            return (url.to_string(), Some(host));
+        }
+
+        (endpoint.to_owned(), None)
+    }
+}
+
+#[derive(Deserialize)]
 struct AdvisorApp {
     name: String,
     location: String,
-    token: String,
+    token: Option<Secret>,
+    token_source: Option<String>,
+    oauth: Option<OAuthConfig>,
+    http: Option<HttpConfig>,
+    #[serde(skip)]
+    oauth_cache: Mutex<Option<CachedToken>>,
+    #[serde(skip)]
+    client_cache: Mutex<Option<surf::Client>>,
+}
+
+impl std::fmt::Debug for AdvisorApp {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("AdvisorApp")
+            .field("name", &self.name)
+            .field("location", &self.location)
+            .field("token", &self.token)
+            .field("token_source", &self.token_source)
+            .field("oauth", &self.oauth)
+            .field("http", &self.http)
+            .finish()
+    }
 }
 
 struct StringWriter {
@@ -70,42 +261,258 @@ impl std::io::Write for StringWriter {
 
 
 impl AdvisorApp {
-    async fn run(&self, command: Command) -> SnafuResult<String> {
+    async fn run(&self, command: Command, output: OutputFormat, hooks: &[Hook]) -> SnafuResult<String> {
         use Command::*;
 
-        match command {
-            Healthcheck => get(self.healthcheck(), Authentication::None).await,
-            ShowPeople => self.show_people().await,
+        self.run_hooks(hooks, "pre", &command)?;
+
+        let result = match &command {
+            Healthcheck => self.get(self.healthcheck(), Authentication::None).await,
+            ShowPeople => self.show_people(output).await,
+            DeletePerson{email} => {
+                let url = format!("{}/admin/people/{}", self.location, email);
+                self.request(HttpMethod::Delete, url, self.authentication(), None).await
+                    .map(|(status, body)| format!("{} {}", status, body))
+            },
+            CreatePerson(params) => {
+                let url = format!("{}/admin/people", self.location);
+                let body = serde_json::to_value(params).or_else(|_| RemoteAPIError.fail())?;
+                self.request(HttpMethod::Post, url, self.authentication(), Some(body)).await
+                    .map(|(status, body)| format!("{} {}", status, body))
+            },
+            AddPersonToQuestionnaire{id, email} => {
+                let url = format!("{}/admin/questionnaires/{}/people/{}", self.location, id, email);
+                self.request(HttpMethod::Put, url, self.authentication(), None).await
+                    .map(|(status, body)| format!("{} {}", status, body))
+            },
+            RemovePersonFromQuestionnaire{id, email} => {
+                let url = format!("{}/admin/questionnaires/{}/people/{}", self.location, id, email);
+                self.request(HttpMethod::Delete, url, self.authentication(), None).await
+                    .map(|(status, body)| format!("{} {}", status, body))
+            },
                 _ => Err(Error::CommandNotFound),
+        };
+
+        if result.is_ok() {
+            if let Err(e) = self.run_hooks(hooks, "post", &command) {
+                println!("Warning: {}", e);
+            }
+        }
+
+        result
+    }
+
+    fn run_hooks(&self, hooks: &[Hook], on: &str, command: &Command) -> SnafuResult<()> {
+        for hook in hooks {
+            if !hook.applies_to(on, command) {
+                continue;
+            }
+
+            let status = ShellCommand::new("sh")
+                .arg("-c")
+                .arg(&hook.run)
+                .env("ADVISOR_COMMAND", command.name())
+                .env("ADVISOR_APP", &self.name)
+                .envs(command.email().map(|email| ("ADVISOR_EMAIL", email.to_owned())))
+                .envs(command.questionnaire_id().map(|id| ("ADVISOR_QUESTIONNAIRE_ID", id.to_owned())))
+                .status();
+
+            match (on, status) {
+                ("pre", Ok(status)) if !status.success() => return HookFailed.fail(),
+                ("pre", Err(_)) => return HookFailed.fail(),
+                (_, Err(_)) => return PostHookFailed.fail(),
+                _ => {},
+            }
         }
+
+        Ok(())
     }
 
     fn healthcheck(&self) -> String {
         format!("{}/healthcheck", self.location)
     }
 
-    async fn show_people(&self) -> SnafuResult<String> {
+    fn authentication(&self) -> Authentication {
+        if let Some(oauth) = &self.oauth {
+            Authentication::ClientCredentials {
+                client_id: oauth.client_id.clone(),
+                client_secret: oauth.client_secret.clone(),
+                token_url: oauth.token_url.clone(),
+                scope: oauth.scope.clone(),
+            }
+        } else if let Some(token) = self.resolve_token() {
+            Authentication::Token(token)
+        } else {
+            Authentication::None
+        }
+    }
+
+    fn resolve_token(&self) -> Option<Secret> {
+        match self.token_source.as_deref() {
+            Some("keyring") => keyring::Keyring::new("advisor-cli", &self.name).get_password().ok().map(Secret),
+            _ => self.token.clone(),
+        }
+    }
+
+    async fn show_people(&self, output: OutputFormat) -> SnafuResult<String> {
         let url = format!("{}/admin/people", self.location);
 
-        let raw = get(url, Authentication::Token(self.token.clone())).await.expect("read from API");
+        let raw = self.get(url, self.authentication()).await.expect("read from API");
 
         let people: People = serde_json::from_str(&raw).expect("Was not able to read API response as JSON");
 
-        let mut table = Table::new();
-        table.add_row(row!["Name", "Email", "Is mentor"]);
+        match output {
+            OutputFormat::Table => {
+                let mut table = Table::new();
+                table.add_row(row!["Name", "Email", "Is mentor"]);
+
+                for person in people {
+                    table.add_row(row![person.name, person.email, person.is_mentor]);
+                }
+                let mut writer = StringWriter::new();
+                table.print(&mut writer).expect("Was not able to write to buffer");
+
+                Result::Ok(writer.content)
+            },
+            OutputFormat::Json => {
+                serde_json::to_string_pretty(&people).or_else(|_| RemoteAPIError.fail())
+            },
+            OutputFormat::Csv => {
+                Result::Ok(people_to_csv(&people))
+            },
+        }
+    }
+
+    async fn get(&self, endpoint: String, auth: Authentication) -> SnafuResult<String> {
+        self.request(HttpMethod::Get, endpoint, auth, None).await.map(|(_status, body)| body)
+    }
+
+    fn http_config(&self) -> HttpConfig {
+        self.http.clone().unwrap_or_default()
+    }
+
+    fn client(&self) -> surf::Client {
+        let mut cache = self.client_cache.lock().unwrap();
+        if let Some(client) = cache.as_ref() {
+            return client.clone();
+        }
+
+        let http = self.http_config();
+
+        if let Some(proxy) = &http.proxy {
+            std::env::set_var("HTTPS_PROXY", proxy);
+            std::env::set_var("HTTP_PROXY", proxy);
+        }
+
+        let config = surf::Config::new().set_timeout(Some(http.timeout()));
+        let client: surf::Client = config.try_into().expect("Was not able to build the HTTP client from config");
+
+        *cache = Some(client.clone());
+        client
+    }
+
+    async fn request(&self, method: HttpMethod, endpoint: String, auth: Authentication, body: Option<serde_json::Value>) -> SnafuResult<(surf::StatusCode, String)> {
+        let http = self.http_config();
+        let (endpoint, original_host) = http.apply_dns_overrides(&endpoint);
+        let token = self.bearer_token(auth).await?;
+        let client = self.client();
+
+        let max_retries = if matches!(method, HttpMethod::Get) { http.retries() } else { 0 };
+        let mut attempt = 0;
+        let mut backoff = http.retry_backoff();
+
+        loop {
+            let mut req = match method {
+                HttpMethod::Get => client.get(&endpoint),
+                HttpMethod::Post => client.post(&endpoint),
+                HttpMethod::Put => client.put(&endpoint),
+                HttpMethod::Delete => client.delete(&endpoint),
+            };
+
+            if let Some(token) = &token {
+                req = req.set_header("Authorization", format!("Bearer {}", token.expose_secret()));
+            }
+
+            if let Some(host) = &original_host {
+                req = req.set_header("Host", host);
+            }
+
+            if let Some(body) = &body {
+                req = req.body_json(body).or_else(|_| RemoteAPIError.fail())?;
+            }
+
+            match req.await {
+                Ok(res) if res.status().is_server_error() && attempt < max_retries => {
+                    attempt += 1;
+                    Delay::new(backoff).await;
+                    backoff *= 2;
+                },
+                Ok(mut res) => {
+                    let status = res.status();
+                    let body = res.body_string().await.or_else(|_| RemoteAPIError.fail())?;
+                    return Ok((status, body));
+                },
+                Err(_) if attempt < max_retries => {
+                    attempt += 1;
+                    Delay::new(backoff).await;
+                    backoff *= 2;
+                },
+                Err(_) => return RemoteAPIError.fail(),
+            }
+        }
+    }
+
+    async fn bearer_token(&self, auth: Authentication) -> SnafuResult<Option<Secret>> {
+        match auth {
+            Authentication::None => Ok(None),
+            Authentication::Token(token) => Ok(Some(token)),
+            Authentication::ClientCredentials { client_id, client_secret, token_url, scope } => {
+                self.client_credentials_token(client_id, client_secret, token_url, scope).await.map(Some)
+            }
+        }
+    }
+
+    async fn client_credentials_token(&self, client_id: Secret, client_secret: Secret, token_url: String, scope: Option<String>) -> SnafuResult<Secret> {
+        const EXPIRY_MARGIN: Duration = Duration::from_secs(30);
+
+        if let Some(cached) = self.oauth_cache.lock().unwrap().as_ref() {
+            if cached.expires_at > Instant::now() + EXPIRY_MARGIN {
+                return Ok(cached.access_token.clone());
+            }
+        }
 
-        for person in people {
-            table.add_row(row![person.name, person.email, person.is_mentor]);
+        let mut body = format!(
+            "grant_type=client_credentials&client_id={}&client_secret={}",
+            form_urlencode(client_id.expose_secret()),
+            form_urlencode(client_secret.expose_secret()),
+        );
+        if let Some(scope) = &scope {
+            body.push_str(&format!("&scope={}", form_urlencode(scope)));
         }
-        let mut output = StringWriter::new();
-        table.print(&mut output).expect("Was not able to write to buffer");
 
-        Result::Ok(output.content)
+        let mut res = surf::post(&token_url)
+            .set_header("Content-Type", "application/x-www-form-urlencoded")
+            .body_string(body)
+            .await
+            .or_else(|_| AuthenticationFailed.fail())?;
+
+        let raw = res.body_string().await.or_else(|_| AuthenticationFailed.fail())?;
+
+        let response: TokenResponse = serde_json::from_str(&raw).or_else(|_| AuthenticationFailed.fail())?;
+
+        let access_token = Secret(response.access_token);
+
+        *self.oauth_cache.lock().unwrap() = Some(CachedToken {
+            access_token: access_token.clone(),
+            expires_at: Instant::now() + Duration::from_secs(response.expires_in),
+        });
+
+        Ok(access_token)
     }
 }
 
 
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug)]
 struct Person {
     name: String,
     email: String,
@@ -114,28 +521,53 @@ struct Person {
 
 type People = Vec<Person>;
 
-#[derive(Deserialize, Debug)]
-struct Config {
-    default: Option<String>,
-    apps: Vec<AdvisorApp>
+fn people_to_csv(people: &[Person]) -> String {
+    let mut csv = String::from("name,email,is_mentor\n");
+    for person in people {
+        csv.push_str(&format!("{},{},{}\n", csv_field(&person.name), csv_field(&person.email), person.is_mentor));
+    }
+    csv
 }
 
-impl Config {
-    fn for_app(&self, name: &str) -> Option<&AdvisorApp> {
-        self.apps.iter().find(|a| a.name == name)
+fn csv_field(value: &str) -> String {
+    if value.contains(',') || value.contains('"') || value.contains('\n') {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_owned()
     }
 }
 
-async fn get(endpoint: String, auth: Authentication) -> SnafuResult<String> {
-    let mut req = surf::get(endpoint);
+#[derive(Deserialize, Debug, Clone)]
+struct Hook {
+    on: String,
+    commands: Option<Vec<String>>,
+    run: String,
+}
+
+impl Hook {
+    fn applies_to(&self, on: &str, command: &Command) -> bool {
+        if self.on != on {
+            return false;
+        }
 
-    if let Authentication::Token(token) = auth {
-        req = req.set_header("Authorization", format!("Bearer {}", token));
+        match &self.commands {
+            Some(commands) => commands.iter().any(|c| c == command.name()),
+            None => true,
+        }
     }
+}
 
-    let mut res = req.timeout(Duration::from_secs(5)).await.or_else(|_| RemoteAPIError.fail() )?;
+#[derive(Deserialize, Debug)]
+struct Config {
+    default: Option<String>,
+    apps: Vec<AdvisorApp>,
+    hooks: Option<Vec<Hook>>,
+}
 
-    res.body_string().await.or_else(|_| RemoteAPIError.fail())
+impl Config {
+    fn for_app(&self, name: &str) -> Option<&AdvisorApp> {
+        self.apps.iter().find(|a| a.name == name)
+    }
 }
 
 fn load_config() -> SnafuResult<Config> {
@@ -147,12 +579,26 @@ fn load_config() -> SnafuResult<Config> {
 
 type PersonParams = std::collections::HashMap<String, String>;
 
+fn form_urlencode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => encoded.push(byte as char),
+            b' ' => encoded.push('+'),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+
+    encoded
+}
+
 fn string(m: &ArgMatches, name: &'static str) -> String {
     m.value_of(name).expect(&format!("'{}' is marked as required", name)).to_owned()
 }
 
 impl Command {
-    fn get() -> (Option<String>, Command) {
+    fn get() -> (Option<String>, OutputFormat, Command) {
         let email = Arg::with_name("email").takes_value(true).required(true).validator(has_at);
 
         let matches = App::new("Advisor-CLI")
@@ -165,21 +611,43 @@ impl Command {
                 .value_name("APP")
                 .help("Which app to act upon. Overrides default in .advisor.json")
                 .takes_value(true))
+            .arg(Arg::with_name("output")
+                .short("o")
+                .long("output")
+                .value_name("FORMAT")
+                .help("How to render the list from 'show people': table, json or csv. Other commands print plain text")
+                .takes_value(true)
+                .possible_values(&["table", "json", "csv"])
+                .default_value("table"))
             .subcommand(SubCommand::with_name("show")
                 .arg(Arg::with_name("kind").takes_value(true).required(true).possible_values(&["people", "questionnaires"]))
             )
             .subcommand(SubCommand::with_name("delete").arg(&email))
+            .subcommand(SubCommand::with_name("create")
+                .arg(Arg::with_name("name").takes_value(true).required(true))
+                .arg(&email)
+                .arg(Arg::with_name("is_mentor").long("mentor").help("Mark the new person as a mentor"))
+            )
             .subcommand(SubCommand::with_name("update")
                 .arg(Arg::with_name("questionnaire_id").takes_value(true).required(true))
                 .arg(Arg::with_name("mode").takes_value(true).required(true).possible_values(&["add", "remove"]))
                 .arg(&email)
             )
             .subcommand(SubCommand::with_name("health"))
+            .subcommand(SubCommand::with_name("login")
+                .arg(Arg::with_name("app").takes_value(true).required(true))
+            )
             .get_matches();
 
         let app_name = matches.value_of("app_name").map(|val| val.to_owned());
 
-        (app_name, Command::parse(&matches))
+        let output = match matches.value_of("output") {
+            Some("json") => OutputFormat::Json,
+            Some("csv") => OutputFormat::Csv,
+            Some("table") | None | Some(_) => OutputFormat::Table,
+        };
+
+        (app_name, output, Command::parse(&matches))
     }
 
     fn parse(matches: &ArgMatches) -> Command {
@@ -189,6 +657,11 @@ impl Command {
             return Healthcheck;
         }
 
+        if let Some(m) = matches.subcommand_matches("login") {
+            let app = string(m, "app");
+            return Login{app};
+        }
+
         if let Some(m) = matches.subcommand_matches("show") {
             match m.value_of("kind") {
                 Some("people") => return ShowPeople,
@@ -202,6 +675,14 @@ impl Command {
             return DeletePerson{ email }
         }
 
+        if let Some(m) = matches.subcommand_matches("create") {
+            let mut params: PersonParams = PersonParams::new();
+            params.insert("name".to_owned(), string(m, "name"));
+            params.insert("email".to_owned(), string(m, "email"));
+            params.insert("is_mentor".to_owned(), m.is_present("is_mentor").to_string());
+            return CreatePerson(params)
+        }
+
         if let Some(m) = matches.subcommand_matches("update") {
             let id = string(m, "questionnaire_id");
             let email = string(m, "email");
@@ -221,24 +702,138 @@ fn has_at(v: String) -> Result<(), String> {
     Err(String::from("The value did not contain the required @ sigil"))
 }
 
+fn login(app: &str) -> SnafuResult<()> {
+    println!("Enter token for {}: ", app);
+
+    let mut token = String::new();
+    std::io::stdin().read_line(&mut token).expect("Was not able to read token from stdin");
+
+    keyring::Keyring::new("advisor-cli", app)
+        .set_password(token.trim())
+        .or_else(|_| KeyringError.fail())
+}
+
 
 
 #[runtime::main]
 async fn main() -> MyResult<()> {
-    let (app_name, c) = Command::get();
+    let (app_name, output, c) = Command::get();
 
     println!("Comand: {:?}", c);
 
+    if let Command::Login{app} = &c {
+        login(app).expect("was not able to store the token in the OS keyring");
+        return Ok(());
+    }
+
     let config = load_config().expect("was not able to find a config");
 
     let name = app_name.or(config.default.clone()).expect("need to specific which app to use");
 
     let app = config.for_app(&name).expect(&format!("unable to find app {}", name));
 
-    match app.run(c).await {
+    let hooks = config.hooks.clone().unwrap_or_default();
+
+    match app.run(c, output, &hooks).await {
         Ok(value) => println!("Success: {}\n", value),
         Err(e) => println!("Failure: {}", e),
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn apply_dns_overrides_leaves_endpoint_untouched_without_config() {
+        let http = HttpConfig::default();
+
+        let (endpoint, host) = http.apply_dns_overrides("https://api.example.com/admin/people");
+
+        assert_eq!(endpoint, "https://api.example.com/admin/people");
+        assert_eq!(host, None);
+    }
+
+    #[test]
+    fn apply_dns_overrides_rewrites_only_the_matching_host() {
+        let http = HttpConfig { resolve: Some(vec!["api.example.com:10.0.0.5".to_owned()]), ..Default::default() };
+
+        let (endpoint, host) = http.apply_dns_overrides("https://api.example.com/admin/people");
+
+        assert_eq!(endpoint, "https://10.0.0.5/admin/people");
+        assert_eq!(host, Some("api.example.com".to_owned()));
+    }
+
+    #[test]
+    fn apply_dns_overrides_does_not_touch_a_host_like_substring_in_the_path() {
+        let http = HttpConfig { resolve: Some(vec!["api:10.0.0.5".to_owned()]), ..Default::default() };
+
+        let (endpoint, host) = http.apply_dns_overrides("https://example.com/admin/people?api_key=secret");
+
+        assert_eq!(endpoint, "https://example.com/admin/people?api_key=secret");
+        assert_eq!(host, None);
+    }
+
+    #[test]
+    fn hook_applies_to_matches_phase_when_no_command_filter_is_set() {
+        let hook = Hook { on: "pre".to_owned(), commands: None, run: "true".to_owned() };
+
+        assert!(hook.applies_to("pre", &Command::DeletePerson{email: "a@example.com".to_owned()}));
+        assert!(!hook.applies_to("post", &Command::DeletePerson{email: "a@example.com".to_owned()}));
+    }
+
+    #[test]
+    fn hook_applies_to_respects_the_commands_filter() {
+        let hook = Hook { on: "pre".to_owned(), commands: Some(vec!["delete".to_owned()]), run: "true".to_owned() };
+
+        assert!(hook.applies_to("pre", &Command::DeletePerson{email: "a@example.com".to_owned()}));
+        assert!(!hook.applies_to("pre", &Command::Healthcheck));
+    }
+
+    #[test]
+    fn csv_field_leaves_plain_values_untouched() {
+        assert_eq!(csv_field("Ferris"), "Ferris");
+    }
+
+    #[test]
+    fn csv_field_quotes_values_containing_a_comma() {
+        assert_eq!(csv_field("Doe, Jane"), "\"Doe, Jane\"");
+    }
+
+    #[test]
+    fn csv_field_escapes_embedded_quotes() {
+        assert_eq!(csv_field("6\" tall"), "\"6\"\" tall\"");
+    }
+
+    #[test]
+    fn people_to_csv_renders_header_and_one_row_per_person() {
+        let people = vec![
+            Person { name: "Ferris".to_owned(), email: "ferris@example.com".to_owned(), is_mentor: true },
+            Person { name: "Doe, Jane".to_owned(), email: "jane@example.com".to_owned(), is_mentor: false },
+        ];
+
+        let csv = people_to_csv(&people);
+
+        assert_eq!(
+            csv,
+            "name,email,is_mentor\nFerris,ferris@example.com,true\n\"Doe, Jane\",jane@example.com,false\n"
+        );
+    }
+
+    #[test]
+    fn form_urlencode_leaves_unreserved_characters_untouched() {
+        assert_eq!(form_urlencode("abcXYZ09-_.~"), "abcXYZ09-_.~");
+    }
+
+    #[test]
+    fn form_urlencode_encodes_a_space_as_a_plus() {
+        assert_eq!(form_urlencode("client secret"), "client+secret");
+    }
+
+    #[test]
+    fn form_urlencode_percent_encodes_reserved_characters() {
+        assert_eq!(form_urlencode("a&b=c+d"), "a%26b%3Dc%2Bd");
+    }
+}